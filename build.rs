@@ -1,16 +1,63 @@
 
 extern crate rustc_version;
+extern crate cargo_metadata;
 
-use rustc_version::{ version, version_meta, Channel };
+use rustc_version::{ version, version_meta, Channel, Version };
+use cargo_metadata::MetadataCommand;
 
 fn main( ) {
     assert!( version( ).unwrap( ).major >= 1 );
 
+    println!( "cargo::rustc-check-cfg=cfg(nightly)" );
+    println!( "cargo::rustc-check-cfg=cfg(stable)" );
+    println!( "cargo::rustc-check-cfg=cfg(beta)" );
+    println!( "cargo::rustc-check-cfg=cfg(dev)" );
+    println!( "cargo::rustc-check-cfg=cfg(has_const_trait_ctor)" );
+    println!( "cargo::rustc-check-cfg=cfg(has_let_else)" );
+
     match version_meta( ).unwrap( ).channel {
         Channel::Nightly => {
             println!( "cargo:rustc-cfg=nightly" );
+            emit_nightly_components( );
+        }
+        Channel::Stable => {
+            println!( "cargo:rustc-cfg=stable" );
+        }
+        Channel::Beta => {
+            println!( "cargo:rustc-cfg=beta" );
+        }
+        Channel::Dev => {
+            println!( "cargo:rustc-cfg=dev" );
         }
-        _ => { }
+    }
+
+    // Strip the pre-release component before comparing, a nightly toolchain reports
+    // e.g. `1.80.0-nightly` which must still satisfy a `>= 1.65` gate.
+    let mut current = version_meta( ).unwrap( ).semver;
+    current.pre = Default::default( );
+
+    if current >= Version::new( 1, 61, 0 ) {
+        println!( "cargo:rustc-cfg=has_const_trait_ctor" );
+    }
+
+    if current >= Version::new( 1, 65, 0 ) {
+        println!( "cargo:rustc-cfg=has_let_else" );
+    }
+}
 
+// Read the `nightly` umbrella feature's member list out of Cargo.toml and activate each member
+// as its own cfg, so the crate's nightly-only code paths (SIMD host comparison, specialization
+// based conversions, ...) can be toggled independently while still all switching on together.
+fn emit_nightly_components( ) {
+    let metadata = MetadataCommand::new( ).exec( ).unwrap( );
+
+    let package = metadata.packages.iter( )
+        .find( |p| p.name == "base_url" )
+        .expect( "base_url package missing from cargo_metadata output" );
+
+    if let Some( members ) = package.features.get( "nightly" ) {
+        for member in members {
+            println!( "cargo:rustc-cfg=feature=\"{}\"", member );
+        }
     }
 }
@@ -42,18 +42,23 @@ admitting potential failures
 
 pub extern crate url;
 
+extern crate idna;
+
 pub extern crate try_from;
 pub use try_from::TryFrom;
 
 pub use url::{ Url, ParseError };
+pub use url::Position;
 
-use url::{ UrlQuery, PathSegmentsMut };
+use url::UrlQuery;
 use url::form_urlencoded::{Parse, Serializer};
 pub use url::{ Host };
 
 use std::str::Split;
-use std::net::IpAddr;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::io;
 use std::fmt::{Formatter, Display, Result as FormatResult};
+use std::ops::{ Index, Range, RangeFrom, RangeTo, RangeFull };
 
 /// A representation of the origin of a BaseUrl
 pub type OriginTuple = ( String, Host<String>, u16 );
@@ -66,6 +71,15 @@ pub enum BaseUrlError {
     ParseError( ParseError ),
 }
 
+impl Display for BaseUrlError {
+    fn fmt( &self, formatter: &mut Formatter ) -> FormatResult {
+        match *self {
+            BaseUrlError::CannotBeBase => write!( formatter, "the given Url cannot be a base" ),
+            BaseUrlError::ParseError( ref e ) => e.fmt( formatter ),
+        }
+    }
+}
+
 /// Any Url which has a host and so can be supplied as a base url
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct BaseUrl {
@@ -494,6 +508,61 @@ impl BaseUrl {
         self.url.domain( )
     }
 
+    /// Decode this BaseUrl's domain back into its Unicode form, for display to a human. `domain( )`
+    /// only ever surfaces the ASCII (Punycode, `xn--`) serialization, since that's what's actually
+    /// on the wire; this runs it through IDNA decoding instead. Returns None if the host is an Ip
+    /// address, or if the stored domain isn't valid IDNA.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use base_url::{ BaseUrl, BaseUrlError, TryFrom };
+    ///
+    ///# fn run( ) -> Result< ( ), BaseUrlError > {
+    /// let mut url = BaseUrl::try_from( "https://example.org/" )?;
+    /// url.set_unicode_host( "münchen.de" ).unwrap( );
+    ///
+    /// assert_eq!( url.unicode_domain( ), Some( "münchen.de".to_string( ) ) );
+    ///# Ok( () )
+    ///# }
+    ///# run( );
+    /// ```
+    pub fn unicode_domain( &self ) -> Option< String > {
+        let domain = self.domain( )?;
+        let ( unicode, result ) = idna::domain_to_unicode( domain );
+
+        match result {
+            Ok( _ ) => Some( unicode ),
+            Err( _ ) => None,
+        }
+    }
+
+    /// Change this BaseUrl's host to a Unicode domain, running it through IDNA ToASCII before
+    /// storing it so the on-the-wire serialization stays spec-compliant. This is the counterpart to
+    /// `unicode_domain( )`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use base_url::{ BaseUrl, BaseUrlError, TryFrom };
+    ///
+    ///# fn run( ) -> Result< ( ), BaseUrlError > {
+    /// let mut url = BaseUrl::try_from( "https://example.org/" )?;
+    ///
+    /// assert!( url.set_unicode_host( "münchen.de" ).is_ok( ) );
+    ///# Ok( () )
+    ///# }
+    ///# run( );
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If the provided host string cannot be parsed a ParseError variant is returned.
+    pub fn set_unicode_host( &mut self, host: &str ) -> Result< (), ParseError > {
+        let ascii = idna::domain_to_ascii( host ).map_err( |_| ParseError::IdnaError )?;
+        self.set_host( &ascii )
+    }
+
     /// Optionally return's the port number of this BaseUrl. Note that whenever a known default port is
     /// included in a url that port is elided. If you require an API which returns port information
     /// including known default port information use `port_or_known_default( )`
@@ -663,12 +732,15 @@ impl BaseUrl {
     ///
     /// url.path_segments_mut( ).clear( ).push( "foo/bar#fragment=no" );
     /// assert_eq!( url.as_str( ), "https://example.org/foo%2Fbar%23fragment=no" );
+    ///
+    /// url.path_segments_mut( ).clear( ).push( "2/100%.png" );
+    /// assert_eq!( url.as_str( ), "https://example.org/2%2F100%25.png" );
     ///# Ok( () )
     ///# }
     ///# run( );
     /// ```
     pub fn path_segments_mut( &mut self ) -> PathSegmentsMut {
-        self.url.path_segments_mut( ).unwrap( )
+        PathSegmentsMut { inner: self.url.path_segments_mut( ).unwrap( ) }
     }
 
     /// Optionally return's this BaseUrl's percent-encoded query string.
@@ -764,6 +836,34 @@ impl BaseUrl {
         self.url.query_pairs_mut( )
     }
 
+    /// Replace this BaseUrl's entire query string with the given (key, value) pairs, percent
+    /// encoding as needed. A convenience over `query_pairs_mut( ).clear( )` followed by repeated
+    /// `append_pair( )` calls.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use base_url::{ BaseUrl, BaseUrlError, TryFrom };
+    ///
+    ///# fn run( ) -> Result< ( ), BaseUrlError > {
+    /// let mut url = BaseUrl::try_from( "https://example.org/issues?page=1" )?;
+    ///
+    /// url.set_query_pairs( vec![ ( "page", "2" ), ( "labels", "E-easy" ) ] );
+    /// assert_eq!( url.as_str( ), "https://example.org/issues?page=2&labels=E-easy" );
+    ///
+    /// // '&', '=' and spaces in keys/values are percent (or '+') encoded rather than corrupting
+    /// // the query string's own pair/key-value delimiters.
+    /// url.set_query_pairs( vec![ ( "a&b", "c=d e" ) ] );
+    /// assert_eq!( url.as_str( ), "https://example.org/issues?a%26b=c%3Dd+e" );
+    ///# Ok( () )
+    ///# }
+    ///# run( );
+    /// ```
+    pub fn set_query_pairs< I, K, V >( &mut self, pairs: I )
+        where I: IntoIterator< Item = ( K, V ) >, K: AsRef< str >, V: AsRef< str > {
+        self.url.query_pairs_mut( ).clear( ).extend_pairs( pairs ).finish( );
+    }
+
     /// Optionally returns this BaseUrl's fragment identifier.
     ///
     /// # Examples
@@ -802,6 +902,235 @@ impl BaseUrl {
         self.url.set_fragment( fragment )
     }
 
+    /// Parse `input` relative to this BaseUrl, following the reference resolution rules laid out
+    /// in RFC 3986. The result may not itself be base-suitable (e.g. it could resolve to a `data:`
+    /// or `mailto:` link), so this returns a plain Url rather than a BaseUrl.
+    ///
+    /// See also `join_base( )`, which re-validates the result and hands back a BaseUrl instead.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use base_url::{ BaseUrl, BaseUrlError, TryFrom };
+    ///
+    ///# fn run( ) -> Result< ( ), BaseUrlError > {
+    /// let url = BaseUrl::try_from( "https://example.org/foo/bar" )?;
+    ///
+    /// assert_eq!( url.join( "baz" )?.as_str( ), "https://example.org/foo/baz" );
+    /// assert_eq!( url.join( "/baz" )?.as_str( ), "https://example.org/baz" );
+    /// assert_eq!( url.join( "../baz" )?.as_str( ), "https://example.org/baz" );
+    ///
+    /// let dir = BaseUrl::try_from( "https://example.org/a/b/" )?;
+    /// assert_eq!( dir.join( "c.png" )?.as_str( ), "https://example.org/a/b/c.png" );
+    ///
+    /// let file = BaseUrl::try_from( "https://example.org/a/b.html" )?;
+    /// assert_eq!( file.join( "c.png" )?.as_str( ), "https://example.org/a/c.png" );
+    ///
+    /// assert_eq!( url.join( "mailto:nobody@example.org" )?.as_str( ), "mailto:nobody@example.org" );
+    ///# Ok( () )
+    ///# }
+    ///# run( );
+    /// ```
+    pub fn join( &self, input: &str ) -> Result< Url, ParseError > {
+        self.url.join( input )
+    }
+
+    /// Like `join( )`, but re-validates the resolved Url as a BaseUrl, returning
+    /// `BaseUrlError::CannotBeBase` if `input` turned out to be an absolute reference to something
+    /// that isn't base-suitable (e.g. a `data:` or `mailto:` link) instead of handing back a Url
+    /// that can't itself be used as a base for further joins.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use base_url::{ BaseUrl, BaseUrlError, TryFrom };
+    ///
+    ///# fn run( ) -> Result< ( ), BaseUrlError > {
+    /// let url = BaseUrl::try_from( "https://example.org/foo/bar" )?;
+    ///
+    /// assert_eq!( url.join_base( "baz" )?.as_str( ), "https://example.org/foo/baz" );
+    /// assert_eq!( url.join_base( "mailto:nobody@example.org" ), Err( BaseUrlError::CannotBeBase ) );
+    ///# Ok( () )
+    ///# }
+    ///# run( );
+    /// ```
+    pub fn join_base( &self, input: &str ) -> Result< BaseUrl, BaseUrlError > {
+        let joined = self.url.join( input )?;
+        BaseUrl::try_from( joined )
+    }
+
+    /// Return a copy of this BaseUrl stripped down to just its scheme and authority, with the path,
+    /// query and fragment cleared, matching the common "get the base URL" recipe. `self` is left
+    /// unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use base_url::{ BaseUrl, BaseUrlError, TryFrom };
+    ///
+    ///# fn run( ) -> Result< ( ), BaseUrlError > {
+    /// let url = BaseUrl::try_from( "https://github.com/rust-lang/cargo?asdf" )?;
+    ///
+    /// assert_eq!( url.to_base( ).as_str( ), "https://github.com/" );
+    ///# Ok( () )
+    ///# }
+    ///# run( );
+    /// ```
+    pub fn to_base( &self ) -> BaseUrl {
+        let mut base = self.clone( );
+
+        base.path_segments_mut( ).clear( );
+        base.set_query( None );
+        base.set_fragment( None );
+
+        base
+    }
+
+    /// Compute a relative reference that, when resolved against this BaseUrl with `join( )`, would
+    /// produce `target`. Returns `None` if the two BaseUrls don't share an origin (scheme, host and
+    /// port all have to match), since no relative reference could bridge that gap.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use base_url::{ BaseUrl, BaseUrlError, TryFrom };
+    ///
+    ///# fn run( ) -> Result< ( ), BaseUrlError > {
+    /// let base = BaseUrl::try_from( "https://example.org/a/b/" )?;
+    /// let target = BaseUrl::try_from( "https://example.org/a/c/d" )?;
+    ///
+    /// assert_eq!( base.make_relative( &target ), Some( "../c/d".to_string( ) ) );
+    ///
+    /// let other = BaseUrl::try_from( "https://example.com/a/b/" )?;
+    /// assert_eq!( base.make_relative( &other ), None );
+    ///
+    /// // A target whose own file name happens to match a deeper base segment must not inflate
+    /// // the shared-prefix count.
+    /// let base = BaseUrl::try_from( "https://example.org/docs/v1/guide" )?;
+    /// let target = BaseUrl::try_from( "https://example.org/docs/v1" )?;
+    /// let relative = base.make_relative( &target ).unwrap( );
+    /// assert_eq!( base.join( &relative )?.as_str( ), target.as_str( ) );
+    ///# Ok( () )
+    ///# }
+    ///# run( );
+    /// ```
+    pub fn make_relative( &self, target: &BaseUrl ) -> Option< String > {
+        if self.origin( ) != target.origin( ) {
+            return None;
+        }
+
+        let mut relative = String::new( );
+
+        let mut base_segments = self.path_segments( ).collect::< Vec< _ > >( );
+        let target_segments = target.path_segments( ).collect::< Vec< _ > >( );
+
+        // The last base segment is a file name (unless the base path ends in '/', in which case
+        // it's already an empty trailing segment), it plays no part in the shared directory prefix.
+        base_segments.pop( );
+
+        // The target's own last segment is a file name too, and must not be counted as part of
+        // the shared directory prefix even when it happens to match a deeper base segment.
+        let target_dir_segments = &target_segments[ ..target_segments.len( ) - 1 ];
+
+        let shared_prefix_len = base_segments.iter( ).zip( target_dir_segments.iter( ) )
+            .take_while( |( b, t )| b == t )
+            .count( );
+
+        for _ in shared_prefix_len..base_segments.len( ) {
+            relative.push_str( "../" );
+        }
+
+        relative.push_str( &target_segments[ shared_prefix_len.. ].join( "/" ) );
+
+        // An empty reference would resolve back to the base's own query, so point at the
+        // directory explicitly when the base has a query and the target doesn't.
+        if relative.is_empty( ) && target.query( ).is_none( ) && self.query( ).is_some( ) {
+            relative.push_str( "./" );
+        }
+
+        if let Some( query ) = target.query( ) {
+            relative.push( '?' );
+            relative.push_str( query );
+        }
+
+        if let Some( fragment ) = target.fragment( ) {
+            relative.push( '#' );
+            relative.push_str( fragment );
+        }
+
+        Some( relative )
+    }
+
+    /// Resolve this BaseUrl to a list of addresses ready to be handed to `TcpStream::connect( )`.
+    /// `default_port` is consulted if neither an explicit port nor a known default port (see
+    /// `port_or_known_default( )`) is available.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use base_url::{ BaseUrl, BaseUrlError, TryFrom };
+    ///
+    ///# fn run( ) -> Result< ( ), BaseUrlError > {
+    /// let url = BaseUrl::try_from( "http://127.0.0.1:8080/" )?;
+    ///
+    /// assert_eq!( url.socket_addrs( || None ).unwrap( )[ 0 ].port( ), 8080 );
+    ///# Ok( () )
+    ///# }
+    ///# run( );
+    /// ```
+    pub fn socket_addrs( &self, default_port: impl Fn( ) -> Option< u16 > ) -> io::Result< Vec< SocketAddr > > {
+        let port = self.port_or_known_default( )
+            .or_else( default_port )
+            .ok_or_else( || io::Error::new( io::ErrorKind::Other, "No port number" ) )?;
+
+        Ok( match self.host( ) {
+            Host::Domain( domain ) => ( domain, port ).to_socket_addrs( )?.collect( ),
+            Host::Ipv4( ip ) => vec![ SocketAddr::from( ( ip, port ) ) ],
+            Host::Ipv6( ip ) => vec![ SocketAddr::from( ( ip, port ) ) ],
+        } )
+    }
+
+}
+
+/// A chainable wrapper around rust-url's `PathSegmentsMut`, returned by
+/// `BaseUrl::path_segments_mut( )`. Exposes the same builder methods, each of which keeps the
+/// underlying Url in sync and returns `&mut Self` so calls can be chained.
+pub struct PathSegmentsMut<'a> {
+    inner: url::PathSegmentsMut<'a>,
+}
+
+impl<'a> PathSegmentsMut<'a> {
+
+    /// Remove all of this BaseUrl's path segments, leaving just the initial '/'.
+    pub fn clear( &mut self ) -> &mut Self {
+        self.inner.clear( );
+        self
+    }
+
+    /// Remove this BaseUrl's last path segment.
+    pub fn pop( &mut self ) -> &mut Self {
+        self.inner.pop( );
+        self
+    }
+
+    /// Remove this BaseUrl's last path segment if it is empty.
+    pub fn pop_if_empty( &mut self ) -> &mut Self {
+        self.inner.pop_if_empty( );
+        self
+    }
+
+    /// Append a path segment, percent-encoding any '/' and '%' characters it contains.
+    pub fn push( &mut self, segment: &str ) -> &mut Self {
+        self.inner.push( segment );
+        self
+    }
+
+    /// Append each segment yielded by `iter`, in order.
+    pub fn extend< I >( &mut self, iter: I ) -> &mut Self
+        where I: IntoIterator, I::Item: AsRef< str > {
+        self.inner.extend( iter );
+        self
+    }
 }
 
 impl Display for BaseUrl {
@@ -809,3 +1138,82 @@ impl Display for BaseUrl {
         self.url.fmt( formatter )
     }
 }
+
+/// Cheaply slice out a contiguous range of this BaseUrl's already-serialized string, without
+/// recomputing or re-parsing anything.
+///
+/// # Examples
+///
+/// ```rust
+/// use base_url::{ BaseUrl, BaseUrlError, Position, TryFrom };
+///
+///# fn run( ) -> Result< ( ), BaseUrlError > {
+/// let url = BaseUrl::try_from( "https://example.org/foo?page=2" )?;
+///
+/// assert_eq!( &url[ Position::BeforePath.. ], "/foo?page=2" );
+/// assert_eq!( &url[ ..Position::BeforeHost ], "https://" );
+///# Ok( () )
+///# }
+///# run( );
+/// ```
+impl Index< Range< Position > > for BaseUrl {
+    type Output = str;
+
+    fn index( &self, range: Range< Position > ) -> &str {
+        &self.url[ range ]
+    }
+}
+
+impl Index< RangeFrom< Position > > for BaseUrl {
+    type Output = str;
+
+    fn index( &self, range: RangeFrom< Position > ) -> &str {
+        &self.url[ range ]
+    }
+}
+
+impl Index< RangeTo< Position > > for BaseUrl {
+    type Output = str;
+
+    fn index( &self, range: RangeTo< Position > ) -> &str {
+        &self.url[ range ]
+    }
+}
+
+impl Index< RangeFull > for BaseUrl {
+    type Output = str;
+
+    fn index( &self, range: RangeFull ) -> &str {
+        &self.url[ range ]
+    }
+}
+
+/// Serialization emits `as_str( )`, mirroring the underlying `Url`'s own serde support.
+/// Deserialization parses the incoming string and runs it through the same `TryFrom<Url>` check
+/// used everywhere else in this crate, so a `ParseError` or a `BaseUrlError::CannotBeBase` both
+/// surface as a descriptive serde error rather than an invalid BaseUrl.
+#[cfg(feature = "serde")]
+mod serde_support {
+    extern crate serde;
+
+    use super::{ BaseUrl, Url };
+    use try_from::TryFrom;
+    use self::serde::{ Serialize, Serializer, Deserialize, Deserializer };
+    use self::serde::de::Error as DeError;
+
+    impl Serialize for BaseUrl {
+        fn serialize< S >( &self, serializer: S ) -> Result< S::Ok, S::Error > where S: Serializer {
+            serializer.serialize_str( self.as_str( ) )
+        }
+    }
+
+    impl<'de> Deserialize<'de> for BaseUrl {
+        fn deserialize< D >( deserializer: D ) -> Result< Self, D::Error > where D: Deserializer<'de> {
+            let serialization = String::deserialize( deserializer )?;
+
+            let url = Url::parse( &serialization ).map_err( DeError::custom )?;
+
+            BaseUrl::try_from( url ).map_err( DeError::custom )
+        }
+    }
+}